@@ -0,0 +1,301 @@
+//! Shell completion script generation for a [`Whsp`]'s registered options.
+//!
+//! [`Whsp::generate_completion`] walks `config_set` (each option's long name
+//! and its `short` name) and emits a
+//! completion script for the requested [`Shell`], mirroring the kind of output
+//! tools like clap produce: flags complete with no argument, `string`/`number`
+//! options request a value, and `NumberRange`/`Regex` validators are surfaced
+//! as a hint for what that value should look like.
+
+use std::io::{self, Write};
+
+use crate::{ConfigOptionBase, Validator, Whsp};
+
+/// A shell flavor that [`Whsp::generate_completion`] can target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    PowerShell,
+    Elvish,
+}
+
+impl<'a> Whsp<'a> {
+    /// Write a completion script for `program_name` to `writer`.
+    pub fn generate_completion<W: Write>(
+        &self,
+        shell: Shell,
+        program_name: &str,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        match shell {
+            Shell::Bash => self.write_bash_completion(program_name, writer),
+            Shell::Zsh => self.write_zsh_completion(program_name, writer),
+            Shell::Fish => self.write_fish_completion(program_name, writer),
+            Shell::PowerShell => self.write_powershell_completion(program_name, writer),
+            Shell::Elvish => self.write_elvish_completion(program_name, writer),
+        }
+    }
+
+    /// Long option names in a stable order, paired with their definitions.
+    fn sorted_options(&self) -> Vec<(&&'a str, &ConfigOptionBase<'a>)> {
+        let mut options: Vec<_> = self.config_set.iter().collect();
+        options.sort_by_key(|(name, _)| **name);
+        options
+    }
+
+    /// A short human-readable hint for the value an option expects, derived
+    /// from its validator, if any.
+    fn value_hint(option: &ConfigOptionBase<'a>) -> Option<String> {
+        match option.validate {
+            Some(Validator::NumberRange(min, max)) => Some(format!("{min}..{max}")),
+            Some(Validator::FloatRange(min, max)) => Some(format!("{min}..{max}")),
+            Some(Validator::Regex(pattern)) => Some(pattern.to_string()),
+            _ => None,
+        }
+    }
+
+    fn write_bash_completion<W: Write>(
+        &self,
+        program_name: &str,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        writeln!(writer, "# bash completion for {program_name}")?;
+        writeln!(writer, "_{program_name}_completions() {{")?;
+        writeln!(writer, "    local cur words")?;
+        writeln!(writer, "    cur=\"${{COMP_WORDS[COMP_CWORD]}}\"")?;
+        write!(writer, "    words=\"")?;
+        for (name, option) in self.sorted_options() {
+            write!(writer, "--{name} ")?;
+            if let Some(short) = option.short {
+                write!(writer, "-{short} ")?;
+            }
+        }
+        writeln!(writer, "\"")?;
+        if self.options.allow_positionals {
+            writeln!(
+                writer,
+                "    COMPREPLY=($(compgen -W \"$words\" -f -- \"$cur\"))"
+            )?;
+        } else {
+            writeln!(writer, "    COMPREPLY=($(compgen -W \"$words\" -- \"$cur\"))")?;
+        }
+        writeln!(writer, "}}")?;
+        writeln!(writer, "complete -F _{program_name}_completions {program_name}")
+    }
+
+    fn write_zsh_completion<W: Write>(
+        &self,
+        program_name: &str,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        writeln!(writer, "#compdef {program_name}")?;
+        writeln!(writer, "_{program_name}() {{")?;
+        writeln!(writer, "    _arguments \\")?;
+        for (name, option) in self.sorted_options() {
+            let takes_value = option.config_type != "boolean";
+            let hint = Self::value_hint(option).unwrap_or_else(|| "value".to_string());
+            let description = option.description.unwrap_or("");
+            let mut spec = format!("'--{name}[{description}]");
+            if takes_value {
+                spec.push_str(&format!(":{hint}:'"));
+            } else {
+                spec.push('\'');
+            }
+            writeln!(writer, "        {spec} \\")?;
+            if let Some(short) = option.short {
+                let mut short_spec = format!("'-{short}[{description}]");
+                if takes_value {
+                    short_spec.push_str(&format!(":{hint}:'"));
+                } else {
+                    short_spec.push('\'');
+                }
+                writeln!(writer, "        {short_spec} \\")?;
+            }
+        }
+        if self.options.allow_positionals {
+            writeln!(writer, "        '*:file:_files'")?;
+        }
+        writeln!(writer, "}}")?;
+        writeln!(writer, "_{program_name} \"$@\"")
+    }
+
+    fn write_fish_completion<W: Write>(
+        &self,
+        program_name: &str,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        for (name, option) in self.sorted_options() {
+            write!(writer, "complete -c {program_name} -l {name}")?;
+            if let Some(short) = option.short {
+                write!(writer, " -s {short}")?;
+            }
+            if option.config_type != "boolean" {
+                write!(writer, " -r")?;
+                if let Some(hint) = Self::value_hint(option) {
+                    write!(writer, " -a '{hint}'")?;
+                }
+            }
+            if let Some(description) = option.description {
+                write!(writer, " -d '{description}'")?;
+            }
+            writeln!(writer)?;
+        }
+        if self.options.allow_positionals {
+            writeln!(writer, "complete -c {program_name} -F")?;
+        }
+        Ok(())
+    }
+
+    fn write_powershell_completion<W: Write>(
+        &self,
+        program_name: &str,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        writeln!(
+            writer,
+            "Register-ArgumentCompleter -Native -CommandName {program_name} -ScriptBlock {{"
+        )?;
+        writeln!(writer, "    param($wordToComplete, $commandAst, $cursorPosition)")?;
+        write!(writer, "    $options = @(")?;
+        for (name, option) in self.sorted_options() {
+            write!(writer, "'--{name}', ")?;
+            if let Some(short) = option.short {
+                write!(writer, "'-{short}', ")?;
+            }
+        }
+        writeln!(writer, ")")?;
+        writeln!(
+            writer,
+            "    $options | Where-Object {{ $_ -like \"$wordToComplete*\" }} | ForEach-Object {{"
+        )?;
+        writeln!(
+            writer,
+            "        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)"
+        )?;
+        writeln!(writer, "    }}")?;
+        writeln!(writer, "}}")
+    }
+
+    fn write_elvish_completion<W: Write>(
+        &self,
+        program_name: &str,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        writeln!(writer, "use edit:completion:arg-completer")?;
+        write!(writer, "set edit:completion:arg-completer[{program_name}] = [@args]{{ put")?;
+        for (name, option) in self.sorted_options() {
+            write!(writer, " --{name}")?;
+            if let Some(short) = option.short {
+                write!(writer, " -{short}")?;
+            }
+        }
+        writeln!(writer, " }}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ConfigOptionBase, Validator, WhspOptions};
+    use std::collections::HashMap;
+
+    fn sample_whsp(allow_positionals: bool) -> Whsp<'static> {
+        let mut whsp = Whsp {
+            config_set: HashMap::new(),
+            short_options: HashMap::new(),
+            options: WhspOptions {
+                allow_positionals,
+                env_prefix: None,
+                usage: None,
+            },
+            subcommands: HashMap::new(),
+        };
+        whsp.opt(HashMap::from([(
+            "name",
+            ConfigOptionBase::new("string", false, Some("n"), Some("your name")),
+        )]))
+        .unwrap();
+        let mut count = ConfigOptionBase::new("number", false, None, Some("how many"));
+        count.validate = Some(Validator::NumberRange(1, 10));
+        whsp.num(HashMap::from([("count", count)])).unwrap();
+        let mut ratio = ConfigOptionBase::new("float", false, None, Some("a ratio"));
+        ratio.validate = Some(Validator::FloatRange(0.0, 1.0));
+        whsp.float(HashMap::from([("ratio", ratio)])).unwrap();
+        whsp.flag(HashMap::from([(
+            "verbose",
+            ConfigOptionBase::new("boolean", false, Some("v"), Some("be noisy")),
+        )]))
+        .unwrap();
+        whsp
+    }
+
+    fn generated(shell: Shell, allow_positionals: bool) -> String {
+        let whsp = sample_whsp(allow_positionals);
+        let mut out = Vec::new();
+        whsp.generate_completion(shell, "myprog", &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn bash_completion_lists_flags_and_respects_positionals() {
+        let script = generated(Shell::Bash, true);
+        assert!(script.contains("complete -F _myprog_completions myprog"));
+        assert!(script.contains("--count"));
+        assert!(script.contains("--name"));
+        assert!(script.contains("-n"));
+        assert!(script.contains("--verbose"));
+        assert!(script.contains("-v"));
+        assert!(script.contains("compgen -W \"$words\" -f --"));
+
+        let script = generated(Shell::Bash, false);
+        assert!(!script.contains("-f --"));
+    }
+
+    #[test]
+    fn zsh_completion_includes_descriptions_and_hints() {
+        let script = generated(Shell::Zsh, true);
+        assert!(script.contains("'--count[how many]:1..10:'"));
+        assert!(script.contains("'--name[your name]:value:'"));
+        assert!(script.contains("'-n[your name]:value:'"));
+        assert!(script.contains("'--verbose[be noisy]'"));
+        assert!(script.contains("'*:file:_files'"));
+
+        let script = generated(Shell::Zsh, false);
+        assert!(!script.contains("'*:file:_files'"));
+    }
+
+    #[test]
+    fn fish_completion_marks_value_taking_options() {
+        let script = generated(Shell::Fish, true);
+        assert!(script.contains("complete -c myprog -l count -r -a '1..10' -d 'how many'"));
+        assert!(script.contains("complete -c myprog -l ratio -r -a '0..1' -d 'a ratio'"));
+        assert!(script.contains("complete -c myprog -l name -s n -r -d 'your name'"));
+        assert!(script.contains("complete -c myprog -l verbose -s v -d 'be noisy'"));
+        assert!(script.contains("complete -c myprog -F"));
+
+        let script = generated(Shell::Fish, false);
+        assert!(!script.contains("complete -c myprog -F"));
+    }
+
+    #[test]
+    fn powershell_completion_registers_argument_completer() {
+        let script = generated(Shell::PowerShell, true);
+        assert!(script.contains("Register-ArgumentCompleter -Native -CommandName myprog"));
+        assert!(script.contains("'--count'"));
+        assert!(script.contains("'--name'"));
+        assert!(script.contains("'-n'"));
+        assert!(script.contains("'--verbose'"));
+        assert!(script.contains("'-v'"));
+    }
+
+    #[test]
+    fn elvish_completion_lists_all_flags() {
+        let script = generated(Shell::Elvish, true);
+        assert!(script.contains("set edit:completion:arg-completer[myprog]"));
+        assert!(script.contains("--count"));
+        assert!(script.contains("--name -n"));
+        assert!(script.contains("--verbose -v"));
+    }
+}
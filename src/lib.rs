@@ -1,10 +1,17 @@
-use std::{borrow::Cow, collections::HashMap, env, fmt};
+use std::{borrow::Cow, collections::HashMap, env, fmt, sync::OnceLock};
+
+use regex::Regex;
+
+pub mod completions;
+pub mod manager;
+pub mod sources;
 
 pub type ConfigType = &'static str;
 
 #[derive(Debug, Clone)]
 pub enum ValidValue<'a> {
     Number(i64),
+    Float(f64),
     String(Cow<'a, str>),
     Boolean(bool),
 }
@@ -13,6 +20,7 @@ pub struct Whsp<'a> {
     pub config_set: HashMap<&'a str, ConfigOptionBase<'a>>,
     pub short_options: HashMap<&'a str, &'a str>,
     pub options: WhspOptions,
+    pub subcommands: HashMap<&'a str, Whsp<'a>>,
 }
 
 #[derive(Debug)]
@@ -30,59 +38,186 @@ pub struct ConfigOptionBase<'a> {
     pub description: Option<&'a str>,
     pub validate: Option<Validator>,
     pub multiple: bool,
+    regex_cache: OnceLock<Regex>,
 }
 
 #[derive(Debug)]
 pub enum Validator {
     NumberRange(i64, i64),
+    FloatRange(f64, f64),
     Regex(&'static str),
     None,
 }
 
 impl<'a> Whsp<'a> {
-    pub fn num(&mut self, fields: HashMap<&'a str, ConfigOptionBase<'a>>) {
+    /// Insert `name`/`option` into `config_set`, compiling and caching its
+    /// `Regex` validator (if any) first so a malformed pattern is rejected
+    /// at registration time instead of panicking or silently never matching
+    /// on first use.
+    fn register(&mut self, name: &'a str, option: ConfigOptionBase<'a>) -> Result<(), String> {
+        option
+            .compile_validator()
+            .map_err(|e| format!("Option {name}: {e}"))?;
+        self.config_set.insert(name, option);
+        Ok(())
+    }
+
+    pub fn num(&mut self, fields: HashMap<&'a str, ConfigOptionBase<'a>>) -> Result<(), String> {
         for (name, mut option) in fields {
             option.config_type = "number";
-            self.config_set.insert(name, option);
+            self.register(name, option)?;
         }
+        Ok(())
     }
 
-    pub fn num_list(&mut self, fields: HashMap<&'a str, ConfigOptionBase<'a>>) {
+    pub fn num_list(
+        &mut self,
+        fields: HashMap<&'a str, ConfigOptionBase<'a>>,
+    ) -> Result<(), String> {
         for (name, mut option) in fields {
             option.config_type = "number";
             option.multiple = true;
-            self.config_set.insert(name, option);
+            self.register(name, option)?;
+        }
+        Ok(())
+    }
+
+    pub fn float(&mut self, fields: HashMap<&'a str, ConfigOptionBase<'a>>) -> Result<(), String> {
+        for (name, mut option) in fields {
+            option.config_type = "float";
+            self.register(name, option)?;
         }
+        Ok(())
     }
 
-    pub fn opt(&mut self, fields: HashMap<&'a str, ConfigOptionBase<'a>>) {
+    pub fn float_list(
+        &mut self,
+        fields: HashMap<&'a str, ConfigOptionBase<'a>>,
+    ) -> Result<(), String> {
+        for (name, mut option) in fields {
+            option.config_type = "float";
+            option.multiple = true;
+            self.register(name, option)?;
+        }
+        Ok(())
+    }
+
+    pub fn opt(&mut self, fields: HashMap<&'a str, ConfigOptionBase<'a>>) -> Result<(), String> {
         for (name, mut option) in fields {
             option.config_type = "string";
-            self.config_set.insert(name, option);
+            self.register(name, option)?;
         }
+        Ok(())
     }
 
-    pub fn opt_list(&mut self, fields: HashMap<&'a str, ConfigOptionBase<'a>>) {
+    pub fn opt_list(
+        &mut self,
+        fields: HashMap<&'a str, ConfigOptionBase<'a>>,
+    ) -> Result<(), String> {
         for (name, mut option) in fields {
             option.config_type = "string";
             option.multiple = true;
-            self.config_set.insert(name, option);
+            self.register(name, option)?;
         }
+        Ok(())
     }
 
-    pub fn flag(&mut self, fields: HashMap<&'a str, ConfigOptionBase<'a>>) {
+    pub fn flag(&mut self, fields: HashMap<&'a str, ConfigOptionBase<'a>>) -> Result<(), String> {
         for (name, mut option) in fields {
             option.config_type = "boolean";
-            self.config_set.insert(name, option);
+            self.register(name, option)?;
         }
+        Ok(())
     }
 
-    pub fn flag_list(&mut self, fields: HashMap<&'a str, ConfigOptionBase<'a>>) {
+    pub fn flag_list(
+        &mut self,
+        fields: HashMap<&'a str, ConfigOptionBase<'a>>,
+    ) -> Result<(), String> {
         for (name, mut option) in fields {
             option.config_type = "boolean";
             option.multiple = true;
-            self.config_set.insert(name, option);
+            self.register(name, option)?;
         }
+        Ok(())
+    }
+
+    /// Register a named subcommand with its own option set. The top-level
+    /// [`Whsp::dispatch`] recognizes `name` as the first non-option
+    /// positional and delegates the remaining args to `sub`.
+    pub fn subcommand(&mut self, name: &'a str, sub: Whsp<'a>) {
+        self.subcommands.insert(name, sub);
+    }
+
+    /// Parse `args`, recognizing this `Whsp`'s own (global) options up
+    /// front and, if any subcommands are registered, treating the first
+    /// token that isn't a recognized or option-shaped argument as the
+    /// subcommand name and delegating the rest of `args` to that
+    /// subcommand's [`parse_raw`](Self::parse_raw). Global options matched
+    /// before the subcommand token are merged into the subcommand's result.
+    /// An option-shaped token (`--foo`/`-f`) that doesn't match a
+    /// registered option is skipped rather than mistaken for a subcommand,
+    /// the same as `parse_raw` silently skips it. If no subcommands are
+    /// registered, this behaves exactly like `parse_raw`.
+    pub fn dispatch(&self, args: &'a [String]) -> Result<DispatchResult<'a>, String> {
+        if self.subcommands.is_empty() {
+            return Ok(DispatchResult {
+                subcommand: None,
+                result: self.parse_raw(args)?,
+            });
+        }
+
+        let mut result = OptionsResult {
+            values: HashMap::new(),
+            lists: HashMap::new(),
+            positionals: Vec::new(),
+        };
+        let mut cursor = ArgCursor { args, i: 0 };
+
+        while cursor.i < args.len() {
+            let arg = cursor.current();
+            if let Some(rest) = arg.strip_prefix("--") {
+                let (key, inline) = split_inline(rest);
+                if let Some(config) = self.config_set.get(key) {
+                    self.apply_match(config, key, inline, &mut cursor, &mut result)?;
+                }
+                cursor.i += 1;
+                continue;
+            } else if let Some(rest) = arg.strip_prefix('-') {
+                let (short, inline) = split_inline(rest);
+                if let Some(&key) = self.short_options.get(short) {
+                    if let Some(config) = self.config_set.get(key) {
+                        self.apply_match(config, key, inline, &mut cursor, &mut result)?;
+                    }
+                }
+                cursor.i += 1;
+                continue;
+            }
+
+            let Some(sub) = self.subcommands.get(arg) else {
+                return Err(format!("Unknown subcommand: {arg}"));
+            };
+            let mut sub_result = sub.parse_raw(&args[cursor.i + 1..])?;
+            for (key, value) in result.values {
+                sub_result.values.entry(key).or_insert(value);
+            }
+            for (key, list) in result.lists {
+                sub_result
+                    .lists
+                    .entry(key)
+                    .or_insert_with(Vec::new)
+                    .splice(0..0, list);
+            }
+            return Ok(DispatchResult {
+                subcommand: Some(arg),
+                result: sub_result,
+            });
+        }
+
+        Ok(DispatchResult {
+            subcommand: None,
+            result,
+        })
     }
 
     pub fn validate_name(
@@ -95,6 +230,9 @@ impl<'a> Whsp<'a> {
                 "Invalid option name: {name}, must be alphanumeric."
             ));
         }
+        option
+            .compile_validator()
+            .map_err(|e| format!("Option {name}: {e}"))?;
         if let Some(short) = option.short {
             if self.short_options.contains_key(short) {
                 return Err(format!("Short option {short} is already in use."));
@@ -114,59 +252,87 @@ impl<'a> Whsp<'a> {
         }
     }
 
-    pub fn parse_raw(&self, args: &'a [String]) -> OptionsResult<'a> {
-        let mut values = HashMap::new();
-        let mut positionals = Vec::new();
-        let mut i = 0;
+    pub fn parse_raw(&self, args: &'a [String]) -> Result<OptionsResult<'a>, String> {
+        let mut result = OptionsResult {
+            values: HashMap::new(),
+            lists: HashMap::new(),
+            positionals: Vec::new(),
+        };
+        let mut cursor = ArgCursor { args, i: 0 };
 
-        while i < args.len() {
-            let arg = &args[i];
-            if let Some(key) = arg.strip_prefix("--") {
+        while cursor.i < args.len() {
+            let arg = cursor.current();
+            if let Some(rest) = arg.strip_prefix("--") {
+                let (key, inline) = split_inline(rest);
                 if let Some(config) = self.config_set.get(key) {
-                    if config.config_type == "boolean" {
-                        values.insert(key, ValidValue::Boolean(true));
-                    } else if i + 1 < args.len() {
-                        let val = &args[i + 1];
-                        values.insert(
-                            key,
-                            match config.config_type {
-                                "string" => ValidValue::String(val.into()),
-                                "number" => ValidValue::Number(val.parse().unwrap()),
-                                _ => panic!("Unknown config type"),
-                            },
-                        );
-                        i += 1;
-                    }
+                    self.apply_match(config, key, inline, &mut cursor, &mut result)?;
                 }
-            } else if let Some(short) = arg.strip_prefix('-') {
+            } else if let Some(rest) = arg.strip_prefix('-') {
+                let (short, inline) = split_inline(rest);
                 if let Some(&key) = self.short_options.get(short) {
                     if let Some(config) = self.config_set.get(key) {
-                        if config.config_type == "boolean" {
-                            values.insert(key, ValidValue::Boolean(true));
-                        } else if i + 1 < args.len() {
-                            let val = &args[i + 1];
-                            values.insert(
-                                key,
-                                match config.config_type {
-                                    "string" => ValidValue::String(val.into()),
-                                    "number" => ValidValue::Number(val.parse().unwrap()),
-                                    _ => panic!("Unknown config type"),
-                                },
-                            );
-                            i += 1;
-                        }
+                        self.apply_match(config, key, inline, &mut cursor, &mut result)?;
                     }
                 }
             } else {
-                positionals.push(arg.as_str());
+                result.positionals.push(arg);
+            }
+            cursor.i += 1;
+        }
+
+        Ok(result)
+    }
+
+    /// Record a single matched option occurrence, consuming the next arg as
+    /// its value when one is needed and no `=value` was inlined. Accumulates
+    /// into `result.lists` for options registered as `multiple` (including
+    /// counting repeated boolean flags) rather than overwriting
+    /// `result.values`. Returns a descriptive error instead of panicking on a
+    /// malformed `number`/`float` value.
+    fn apply_match(
+        &self,
+        config: &ConfigOptionBase<'a>,
+        key: &'a str,
+        inline: Option<&'a str>,
+        cursor: &mut ArgCursor<'a>,
+        result: &mut OptionsResult<'a>,
+    ) -> Result<(), String> {
+        if config.config_type == "boolean" && inline.is_none() {
+            if config.multiple {
+                result
+                    .lists
+                    .entry(key)
+                    .or_default()
+                    .push(ValidValue::Boolean(true));
+            } else {
+                result.values.insert(key, ValidValue::Boolean(true));
             }
-            i += 1;
+            return Ok(());
         }
 
-        OptionsResult {
-            values,
-            positionals,
+        let raw = inline.or_else(|| cursor.next_value());
+        let Some(raw) = raw else { return Ok(()) };
+
+        let value = match config.config_type {
+            "string" => ValidValue::String(raw.into()),
+            "number" => ValidValue::Number(
+                raw.parse()
+                    .map_err(|e| format!("Invalid value for --{key}: {raw:?} is not a number ({e})"))?,
+            ),
+            "float" => ValidValue::Float(
+                raw.parse()
+                    .map_err(|e| format!("Invalid value for --{key}: {raw:?} is not a float ({e})"))?,
+            ),
+            "boolean" => ValidValue::Boolean(raw == "true" || raw == "1"),
+            other => return Err(format!("Unknown config type: {other}")),
+        };
+
+        if config.multiple {
+            result.lists.entry(key).or_default().push(value);
+        } else {
+            result.values.insert(key, value);
         }
+        Ok(())
     }
 
     pub fn validate(&self, o: &HashMap<String, ValidValue>) -> Result<(), String> {
@@ -175,21 +341,44 @@ impl<'a> Whsp<'a> {
                 .config_set
                 .get(field.as_str())
                 .ok_or(format!("Unknown config option: {field}"))?;
-            validate_options(config, value)?;
+            validate_options(field, config, value)?;
+        }
+        Ok(())
+    }
+
+    /// Validate a [`parse_raw`](Self::parse_raw) result, checking every
+    /// element of a `multiple` option's accumulated list as well as each
+    /// single-valued option.
+    pub fn validate_result(&self, result: &OptionsResult) -> Result<(), String> {
+        for (field, value) in &result.values {
+            let config = self
+                .config_set
+                .get(field)
+                .ok_or(format!("Unknown config option: {field}"))?;
+            validate_options(field, config, value)?;
+        }
+        for (field, list) in &result.lists {
+            let config = self
+                .config_set
+                .get(field)
+                .ok_or(format!("Unknown config option: {field}"))?;
+            for value in list {
+                validate_options(field, config, value)?;
+            }
         }
         Ok(())
     }
 
-    pub fn set_defaults_from_env(&mut self) {
+    pub fn set_defaults_from_env(&mut self) -> Result<(), String> {
         if let Some(prefix) = self.options.env_prefix {
             for (key, option) in self.config_set.iter_mut() {
                 let env_key = to_env_key(prefix, key);
                 if let Ok(val) = env::var(&env_key) {
-                    let valid_val = from_env_val(val, option.config_type);
-                    option.default = Some(valid_val);
+                    option.default = Some(from_env_val(val, option.config_type)?);
                 }
             }
         }
+        Ok(())
     }
 }
 
@@ -197,6 +386,7 @@ impl fmt::Display for ValidValue<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             ValidValue::Number(val) => write!(f, "{val}"),
+            ValidValue::Float(val) => write!(f, "{val}"),
             ValidValue::String(val) => write!(f, "{val}"),
             ValidValue::Boolean(val) => write!(f, "{val}"),
         }
@@ -217,16 +407,56 @@ impl<'a> ConfigOptionBase<'a> {
             description,
             validate: None,
             multiple,
+            regex_cache: OnceLock::new(),
+        }
+    }
+
+    /// Compile and cache this option's `Regex` validator, if it has one,
+    /// returning a descriptive error if the pattern is invalid. Called when
+    /// an option is registered through `num`/`opt`/`flag` and their `_list`
+    /// and `float` variants, and by [`Whsp::validate_name`], so a bad
+    /// pattern is rejected before it can ever reach `validate_value`.
+    pub fn compile_validator(&self) -> Result<(), String> {
+        let pattern = match &self.validate {
+            Some(Validator::Regex(pattern)) => *pattern,
+            _ => return Ok(()),
+        };
+        if self.regex_cache.get().is_none() {
+            let compiled = Regex::new(pattern)
+                .map_err(|e| format!("Invalid regex pattern {pattern:?}: {e}"))?;
+            let _ = self.regex_cache.set(compiled);
+        }
+        Ok(())
+    }
+
+    /// The cached compiled pattern. If nothing is cached yet (the option
+    /// never went through `compile_validator`), `pattern` is compiled and
+    /// cached here. A pattern that fails to compile is not cached and yields
+    /// `None`, rather than falling back to some other "never matches" regex
+    /// (which could itself fail to compile and panic).
+    fn compiled_regex(&self, pattern: &'static str) -> Option<&Regex> {
+        if self.regex_cache.get().is_none() {
+            if let Ok(compiled) = Regex::new(pattern) {
+                let _ = self.regex_cache.set(compiled);
+            }
         }
+        self.regex_cache.get()
     }
 
     pub fn validate_value(&self, value: &ValidValue) -> bool {
         if let Some(ref validate) = self.validate {
             match *validate {
-                Validator::Regex(regex) => matches!(value, ValidValue::String(s) if regex == s),
+                Validator::Regex(pattern) => matches!(
+                    value,
+                    ValidValue::String(s)
+                        if self.compiled_regex(pattern).is_some_and(|re| re.is_match(s))
+                ),
                 Validator::NumberRange(min, max) => {
                     matches!(value, ValidValue::Number(num) if *num >= min && *num <= max)
                 },
+                Validator::FloatRange(min, max) => {
+                    matches!(value, ValidValue::Float(num) if *num >= min && *num <= max)
+                },
                 Validator::None => true,
             }
         } else {
@@ -234,6 +464,7 @@ impl<'a> ConfigOptionBase<'a> {
                 (self.config_type, value),
                 ("string", ValidValue::String(_))
                     | ("number", ValidValue::Number(_))
+                    | ("float", ValidValue::Float(_))
                     | ("boolean", ValidValue::Boolean(_))
             )
         }
@@ -244,12 +475,23 @@ pub fn to_env_key(prefix: &str, key: &str) -> String {
     format!("{}_{}", prefix.to_uppercase(), key.to_uppercase())
 }
 
-pub fn from_env_val<'a, E: Into<Cow<'a, str>>>(env: E, config_type: &str) -> ValidValue<'a> {
+pub fn from_env_val<'a, E: Into<Cow<'a, str>>>(
+    env: E,
+    config_type: &str,
+) -> Result<ValidValue<'a>, String> {
+    let env = env.into();
     match config_type {
-        "string" => ValidValue::String(env.into()),
-        "number" => ValidValue::Number(env.into().parse().unwrap()),
-        "boolean" => ValidValue::Boolean(env.into() == "1"),
-        _ => panic!("Unknown config type"),
+        "string" => Ok(ValidValue::String(env)),
+        "number" => env
+            .parse()
+            .map(ValidValue::Number)
+            .map_err(|e| format!("Invalid number {env:?}: {e}")),
+        "float" => env
+            .parse()
+            .map(ValidValue::Float)
+            .map_err(|e| format!("Invalid float {env:?}: {e}")),
+        "boolean" => Ok(ValidValue::Boolean(env == "1" || env == "true")),
+        other => Err(format!("Unknown config type: {other}")),
     }
 }
 
@@ -257,6 +499,7 @@ pub fn to_env_val(value: &ValidValue) -> String {
     match value {
         ValidValue::String(v) => v.to_string(),
         ValidValue::Number(v) => v.to_string(),
+        ValidValue::Float(v) => v.to_string(),
         ValidValue::Boolean(v) => {
             if *v {
                 "1"
@@ -268,15 +511,341 @@ pub fn to_env_val(value: &ValidValue) -> String {
     }
 }
 
-pub fn validate_options(config: &ConfigOptionBase, value: &ValidValue) -> Result<(), String> {
-    if !config.validate_value(value) {
-        return Err(format!("Invalid value {value:?} for option"));
+pub fn validate_options(
+    field: &str,
+    config: &ConfigOptionBase,
+    value: &ValidValue,
+) -> Result<(), String> {
+    if config.validate_value(value) {
+        return Ok(());
+    }
+    match &config.validate {
+        Some(Validator::Regex(pattern)) => Err(format!(
+            "Invalid value {value:?} for option {field}: does not match pattern {pattern:?}"
+        )),
+        _ => Err(format!("Invalid value {value:?} for option {field}")),
     }
-    Ok(())
 }
 
 #[derive(Debug)]
 pub struct OptionsResult<'a> {
     pub values: HashMap<&'a str, ValidValue<'a>>,
+    /// Accumulated values for options registered as `multiple` (via
+    /// `num_list`/`opt_list`/`flag_list`), in the order they were matched.
+    pub lists: HashMap<&'a str, Vec<ValidValue<'a>>>,
     pub positionals: Vec<&'a str>,
 }
+
+/// The outcome of [`Whsp::dispatch`]: which subcommand (if any) matched, and
+/// the parsed options, including any global options matched before the
+/// subcommand token.
+#[derive(Debug)]
+pub struct DispatchResult<'a> {
+    pub subcommand: Option<&'a str>,
+    pub result: OptionsResult<'a>,
+}
+
+/// Split `--flag=value` / `-f=value` style arguments into the flag name and
+/// its inline value, if present.
+fn split_inline(rest: &str) -> (&str, Option<&str>) {
+    match rest.split_once('=') {
+        Some((key, value)) => (key, Some(value)),
+        None => (rest, None),
+    }
+}
+
+/// A cursor over `args`, shared by `parse_raw` and `dispatch` so `apply_match`
+/// can consume the following argument as an option's value without every
+/// caller threading `args`/`i` through separately.
+struct ArgCursor<'a> {
+    args: &'a [String],
+    i: usize,
+}
+
+impl<'a> ArgCursor<'a> {
+    fn current(&self) -> &'a str {
+        self.args[self.i].as_str()
+    }
+
+    /// Consume and return the next argument as the current option's value,
+    /// if one remains.
+    fn next_value(&mut self) -> Option<&'a str> {
+        if self.i + 1 < self.args.len() {
+            self.i += 1;
+            Some(self.args[self.i].as_str())
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn as_str(value: Option<&ValidValue>) -> Option<String> {
+        match value {
+            Some(ValidValue::String(s)) => Some(s.to_string()),
+            _ => None,
+        }
+    }
+
+    fn as_num(value: Option<&ValidValue>) -> Option<i64> {
+        match value {
+            Some(ValidValue::Number(n)) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn make_whsp() -> Whsp<'static> {
+        let mut whsp = Whsp {
+            config_set: HashMap::new(),
+            short_options: HashMap::new(),
+            options: WhspOptions {
+                allow_positionals: false,
+                env_prefix: None,
+                usage: None,
+            },
+            subcommands: HashMap::new(),
+        };
+        whsp.opt(HashMap::from([(
+            "name",
+            ConfigOptionBase::new("string", false, None, None),
+        )]))
+        .unwrap();
+
+        let mut sub = Whsp {
+            config_set: HashMap::new(),
+            short_options: HashMap::new(),
+            options: WhspOptions {
+                allow_positionals: false,
+                env_prefix: None,
+                usage: None,
+            },
+            subcommands: HashMap::new(),
+        };
+        sub.num(HashMap::from([(
+            "count",
+            ConfigOptionBase::new("number", false, None, None),
+        )]))
+        .unwrap();
+        sub.opt(HashMap::from([(
+            "name",
+            ConfigOptionBase::new("string", false, None, None),
+        )]))
+        .unwrap();
+        whsp.subcommand("run", sub);
+        whsp
+    }
+
+    fn args(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn dispatch_parses_nested_subcommand_options() {
+        let whsp = make_whsp();
+        let input = args(&["run", "--count", "3"]);
+        let dispatched = whsp.dispatch(&input).unwrap();
+        assert_eq!(dispatched.subcommand, Some("run"));
+        assert_eq!(as_num(dispatched.result.values.get("count")), Some(3));
+    }
+
+    #[test]
+    fn dispatch_rejects_unknown_subcommand() {
+        let whsp = make_whsp();
+        let input = args(&["bogus", "--count", "3"]);
+        let err = whsp.dispatch(&input).unwrap_err();
+        assert!(err.contains("Unknown subcommand: bogus"));
+    }
+
+    #[test]
+    fn dispatch_merges_global_options_without_overriding_local_ones() {
+        let whsp = make_whsp();
+        let input = args(&["--name", "global-name", "run", "--name", "local-name"]);
+        let dispatched = whsp.dispatch(&input).unwrap();
+        assert_eq!(dispatched.subcommand, Some("run"));
+        assert_eq!(
+            as_str(dispatched.result.values.get("name")),
+            Some("local-name".into())
+        );
+    }
+
+    #[test]
+    fn dispatch_falls_back_to_global_option_when_local_is_unset() {
+        let whsp = make_whsp();
+        let input = args(&["--name", "global-name", "run"]);
+        let dispatched = whsp.dispatch(&input).unwrap();
+        assert_eq!(dispatched.subcommand, Some("run"));
+        assert_eq!(
+            as_str(dispatched.result.values.get("name")),
+            Some("global-name".into())
+        );
+    }
+
+    #[test]
+    fn dispatch_without_subcommands_behaves_like_parse_raw() {
+        let mut whsp = Whsp {
+            config_set: HashMap::new(),
+            short_options: HashMap::new(),
+            options: WhspOptions {
+                allow_positionals: false,
+                env_prefix: None,
+                usage: None,
+            },
+            subcommands: HashMap::new(),
+        };
+        whsp.opt(HashMap::from([(
+            "name",
+            ConfigOptionBase::new("string", false, None, None),
+        )]))
+        .unwrap();
+        let input = args(&["--name", "solo"]);
+        let dispatched = whsp.dispatch(&input).unwrap();
+        assert_eq!(dispatched.subcommand, None);
+        assert_eq!(
+            as_str(dispatched.result.values.get("name")),
+            Some("solo".into())
+        );
+    }
+
+    #[test]
+    fn dispatch_skips_unrecognized_option_instead_of_treating_it_as_a_subcommand() {
+        let whsp = make_whsp();
+        let input = args(&["--typo", "run", "--count", "3"]);
+        let dispatched = whsp.dispatch(&input).unwrap();
+        assert_eq!(dispatched.subcommand, Some("run"));
+        assert_eq!(as_num(dispatched.result.values.get("count")), Some(3));
+    }
+
+    #[test]
+    fn apply_match_accumulates_repeated_multi_value_option() {
+        let mut whsp = Whsp {
+            config_set: HashMap::new(),
+            short_options: HashMap::new(),
+            options: WhspOptions {
+                allow_positionals: false,
+                env_prefix: None,
+                usage: None,
+            },
+            subcommands: HashMap::new(),
+        };
+        whsp.opt_list(HashMap::from([(
+            "tag",
+            ConfigOptionBase::new("string", true, None, None),
+        )]))
+        .unwrap();
+        let input = args(&["--tag", "a", "--tag", "b"]);
+        let result = whsp.parse_raw(&input).unwrap();
+        let tags: Vec<String> = result.lists["tag"]
+            .iter()
+            .map(|v| match v {
+                ValidValue::String(s) => s.to_string(),
+                _ => panic!("expected string"),
+            })
+            .collect();
+        assert_eq!(tags, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn apply_match_accepts_inline_equals_syntax() {
+        let mut whsp = Whsp {
+            config_set: HashMap::new(),
+            short_options: HashMap::new(),
+            options: WhspOptions {
+                allow_positionals: false,
+                env_prefix: None,
+                usage: None,
+            },
+            subcommands: HashMap::new(),
+        };
+        whsp.opt(HashMap::from([(
+            "name",
+            ConfigOptionBase::new("string", false, None, None),
+        )]))
+        .unwrap();
+        let input = args(&["--name=inline-value"]);
+        let result = whsp.parse_raw(&input).unwrap();
+        assert_eq!(as_str(result.values.get("name")), Some("inline-value".into()));
+    }
+
+    #[test]
+    fn apply_match_counts_repeated_boolean_flags() {
+        let mut whsp = Whsp {
+            config_set: HashMap::new(),
+            short_options: HashMap::new(),
+            options: WhspOptions {
+                allow_positionals: false,
+                env_prefix: None,
+                usage: None,
+            },
+            subcommands: HashMap::new(),
+        };
+        whsp.flag_list(HashMap::from([(
+            "verbose",
+            ConfigOptionBase::new("boolean", true, None, None),
+        )]))
+        .unwrap();
+        let input = args(&["--verbose", "--verbose", "--verbose"]);
+        let result = whsp.parse_raw(&input).unwrap();
+        assert_eq!(result.lists["verbose"].len(), 3);
+    }
+
+    #[test]
+    fn parse_raw_returns_descriptive_error_instead_of_panicking_on_bad_number() {
+        let mut whsp = Whsp {
+            config_set: HashMap::new(),
+            short_options: HashMap::new(),
+            options: WhspOptions {
+                allow_positionals: false,
+                env_prefix: None,
+                usage: None,
+            },
+            subcommands: HashMap::new(),
+        };
+        whsp.num(HashMap::from([(
+            "count",
+            ConfigOptionBase::new("number", false, None, None),
+        )]))
+        .unwrap();
+        let input = args(&["--count", "xyz"]);
+        let err = whsp.parse_raw(&input).unwrap_err();
+        assert!(err.contains("--count"));
+        assert!(err.contains("xyz"));
+    }
+
+    #[test]
+    fn parse_raw_returns_descriptive_error_instead_of_panicking_on_bad_float() {
+        let mut whsp = Whsp {
+            config_set: HashMap::new(),
+            short_options: HashMap::new(),
+            options: WhspOptions {
+                allow_positionals: false,
+                env_prefix: None,
+                usage: None,
+            },
+            subcommands: HashMap::new(),
+        };
+        whsp.float(HashMap::from([(
+            "ratio",
+            ConfigOptionBase::new("float", false, None, None),
+        )]))
+        .unwrap();
+        let input = args(&["--ratio", "xyz"]);
+        let err = whsp.parse_raw(&input).unwrap_err();
+        assert!(err.contains("--ratio"));
+        assert!(err.contains("xyz"));
+    }
+
+    #[test]
+    fn float_value_round_trips_through_env_conversion() {
+        let env_val = to_env_val(&ValidValue::Float(2.5));
+        let round_tripped = from_env_val(env_val, "float").unwrap();
+        assert!(matches!(round_tripped, ValidValue::Float(f) if f == 2.5));
+    }
+
+    #[test]
+    fn float_value_displays_without_decoration() {
+        assert_eq!(ValidValue::Float(2.5).to_string(), "2.5");
+    }
+}
@@ -0,0 +1,158 @@
+//! Runtime-scoped option overrides layered on top of a [`Whsp`]'s static
+//! `config_set`.
+//!
+//! An [`OptionManager`] holds values that can be read and overridden at
+//! runtime, with scope inheritance: [`OptionManager::get`] walks up the
+//! parent chain until it finds a value that was explicitly set, falling back
+//! to the option's registered `default`. [`OptionManager::child`] creates a
+//! nested scope that shares its parent without mutating it, so an
+//! application can have a global config with per-context overrides (e.g. per
+//! subcommand or per-request).
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::{ValidValue, Whsp};
+
+/// A runtime-mutable scope of option values, optionally falling back to a
+/// parent scope and finally to the option's registered `default`.
+///
+/// `'a` is the lifetime of the underlying `Whsp`/`ValidValue` data, shared by
+/// every manager in a chain. `'p` is the (usually much shorter) lifetime of
+/// this particular node's borrow of its parent, kept separate from `'a` so
+/// [`child`](Self::child) can borrow `self` for less than `'a` — `self` is
+/// typically a local variable that does not itself live for `'a`.
+pub struct OptionManager<'p, 'a> {
+    whsp: &'a Whsp<'a>,
+    values: RwLock<HashMap<&'a str, ValidValue<'a>>>,
+    parent: Option<&'p OptionManager<'p, 'a>>,
+}
+
+impl<'p, 'a> OptionManager<'p, 'a> {
+    /// Create a root manager with no overrides and no parent.
+    pub fn new(whsp: &'a Whsp<'a>) -> OptionManager<'a, 'a> {
+        OptionManager {
+            whsp,
+            values: RwLock::new(HashMap::new()),
+            parent: None,
+        }
+    }
+
+    /// Create a nested scope sharing this manager as its parent. Values set
+    /// on the child shadow the parent; values set on the parent afterwards
+    /// are still visible through the child wherever the child hasn't
+    /// overridden them.
+    pub fn child(&self) -> OptionManager<'_, 'a> {
+        OptionManager {
+            whsp: self.whsp,
+            values: RwLock::new(HashMap::new()),
+            parent: Some(self),
+        }
+    }
+
+    /// Set a value in this scope, shadowing the parent chain and the
+    /// option's default.
+    pub fn set(&self, name: &'a str, value: ValidValue<'a>) {
+        self.values.write().unwrap().insert(name, value);
+    }
+
+    /// Remove this scope's own override for `name`, if any. Lookups fall
+    /// back to the parent chain and then the default, same as if it had
+    /// never been set.
+    pub fn unset(&self, name: &str) {
+        self.values.write().unwrap().remove(name);
+    }
+
+    /// Resolve `name` by checking this scope, then each parent in turn, then
+    /// the option's registered default.
+    pub fn get(&self, name: &str) -> Option<ValidValue<'a>> {
+        if let Some(value) = self.values.read().unwrap().get(name) {
+            return Some(value.clone());
+        }
+        if let Some(parent) = self.parent {
+            if let Some(value) = parent.get(name) {
+                return Some(value);
+            }
+        }
+        self.whsp.config_set.get(name)?.default.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ConfigOptionBase, WhspOptions};
+
+    fn make_whsp() -> Whsp<'static> {
+        let mut whsp = Whsp {
+            config_set: HashMap::new(),
+            short_options: HashMap::new(),
+            options: WhspOptions {
+                allow_positionals: false,
+                env_prefix: None,
+                usage: None,
+            },
+            subcommands: HashMap::new(),
+        };
+        let mut name = ConfigOptionBase::new("string", false, None, None);
+        name.default = Some(ValidValue::String("default-name".into()));
+        whsp.opt(HashMap::from([("name", name)])).unwrap();
+        whsp
+    }
+
+    fn as_str(value: Option<ValidValue>) -> Option<String> {
+        match value {
+            Some(ValidValue::String(s)) => Some(s.to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn new_accepts_a_stack_local_whsp_without_leaking() {
+        let whsp = make_whsp();
+        let manager = OptionManager::new(&whsp);
+        assert_eq!(as_str(manager.get("name")), Some("default-name".into()));
+    }
+
+    #[test]
+    fn get_falls_back_to_default_when_nothing_is_set() {
+        let whsp = make_whsp();
+        let manager = OptionManager::new(&whsp);
+        assert_eq!(as_str(manager.get("name")), Some("default-name".into()));
+    }
+
+    #[test]
+    fn child_falls_back_to_parent_value() {
+        let whsp = make_whsp();
+        let parent = OptionManager::new(&whsp);
+        parent.set("name", ValidValue::String("from-parent".into()));
+        let child = parent.child();
+        assert_eq!(as_str(child.get("name")), Some("from-parent".into()));
+    }
+
+    #[test]
+    fn child_shadows_parent_value() {
+        let whsp = make_whsp();
+        let parent = OptionManager::new(&whsp);
+        parent.set("name", ValidValue::String("from-parent".into()));
+        let child = parent.child();
+        child.set("name", ValidValue::String("from-child".into()));
+        assert_eq!(as_str(child.get("name")), Some("from-child".into()));
+        assert_eq!(as_str(parent.get("name")), Some("from-parent".into()));
+    }
+
+    #[test]
+    fn unset_reverts_to_parent_then_default() {
+        let whsp = make_whsp();
+        let parent = OptionManager::new(&whsp);
+        let child = parent.child();
+        child.set("name", ValidValue::String("from-child".into()));
+        child.unset("name");
+        assert_eq!(as_str(child.get("name")), Some("default-name".into()));
+
+        parent.set("name", ValidValue::String("from-parent".into()));
+        child.set("name", ValidValue::String("from-child".into()));
+        child.unset("name");
+        assert_eq!(as_str(child.get("name")), Some("from-parent".into()));
+    }
+}
@@ -0,0 +1,259 @@
+//! Layered configuration sources for [`Whsp`].
+//!
+//! A final option value is resolved by overlaying sources from lowest to
+//! highest precedence: built-in `default` < config file < environment
+//! (`env_prefix`) < CLI args (`parse_raw`). [`Whsp::load_layered`] walks a
+//! list of [`Source`]s in the order they are given, letting each one
+//! override the values set by the sources before it, so the sources must be
+//! listed in that order to get that precedence.
+
+use std::collections::HashMap;
+use std::fs;
+
+use crate::{from_env_val, to_env_key, OptionsResult, ValidValue, Whsp};
+
+/// A single configuration source, applied in the order given to
+/// [`Whsp::load_layered`]. Later sources in the slice take precedence over
+/// earlier ones.
+pub enum Source<'p> {
+    Toml(&'p str),
+    Json(&'p str),
+    Env,
+    Args,
+}
+
+impl<'a> Whsp<'a> {
+    /// Resolve option values across `sources`, in order, with later sources
+    /// overriding earlier ones. `args` is used wherever `Source::Args`
+    /// appears. The merged result is validated before it is returned.
+    pub fn load_layered(
+        &self,
+        sources: &[Source],
+        args: &'a [String],
+    ) -> Result<OptionsResult<'a>, String> {
+        let mut values: HashMap<&'a str, ValidValue<'a>> = HashMap::new();
+        let mut lists: HashMap<&'a str, Vec<ValidValue<'a>>> = HashMap::new();
+        let mut positionals = Vec::new();
+
+        for (name, option) in &self.config_set {
+            if let Some(default) = &option.default {
+                values.insert(name, default.clone());
+            }
+        }
+
+        for source in sources {
+            match source {
+                Source::Toml(path) => self.layer_toml(path, &mut values)?,
+                Source::Json(path) => self.layer_json(path, &mut values)?,
+                Source::Env => self.layer_env(&mut values),
+                Source::Args => {
+                    let parsed = self.parse_raw(args)?;
+                    values.extend(parsed.values);
+                    lists = parsed.lists;
+                    positionals = parsed.positionals;
+                }
+            }
+        }
+
+        let result = OptionsResult {
+            values,
+            lists,
+            positionals,
+        };
+        self.validate_result(&result)?;
+
+        Ok(result)
+    }
+
+    fn layer_toml(
+        &self,
+        path: &str,
+        values: &mut HashMap<&'a str, ValidValue<'a>>,
+    ) -> Result<(), String> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| format!("Could not read {path}: {e}"))?;
+        let table: toml::Value =
+            toml::from_str(&contents).map_err(|e| format!("Invalid TOML in {path}: {e}"))?;
+        let Some(table) = table.as_table() else {
+            return Err(format!("{path} must contain a top-level table"));
+        };
+        for (name, option) in &self.config_set {
+            if let Some(raw) = table.get(*name) {
+                let string_val = match raw {
+                    toml::Value::String(s) => s.clone(),
+                    toml::Value::Integer(i) => i.to_string(),
+                    toml::Value::Boolean(b) => {
+                        if *b { "1".to_string() } else { "0".to_string() }
+                    }
+                    other => other.to_string(),
+                };
+                values.insert(name, from_env_val(string_val, option.config_type)?);
+            }
+        }
+        Ok(())
+    }
+
+    fn layer_json(
+        &self,
+        path: &str,
+        values: &mut HashMap<&'a str, ValidValue<'a>>,
+    ) -> Result<(), String> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| format!("Could not read {path}: {e}"))?;
+        let object: serde_json::Value =
+            serde_json::from_str(&contents).map_err(|e| format!("Invalid JSON in {path}: {e}"))?;
+        let Some(object) = object.as_object() else {
+            return Err(format!("{path} must contain a top-level object"));
+        };
+        for (name, option) in &self.config_set {
+            if let Some(raw) = object.get(*name) {
+                let string_val = match raw {
+                    serde_json::Value::String(s) => s.clone(),
+                    serde_json::Value::Number(n) => n.to_string(),
+                    serde_json::Value::Bool(b) => {
+                        if *b { "1".to_string() } else { "0".to_string() }
+                    }
+                    other => other.to_string(),
+                };
+                values.insert(name, from_env_val(string_val, option.config_type)?);
+            }
+        }
+        Ok(())
+    }
+
+    fn layer_env(&self, values: &mut HashMap<&'a str, ValidValue<'a>>) {
+        let Some(prefix) = self.options.env_prefix else {
+            return;
+        };
+        for (name, option) in &self.config_set {
+            let env_key = to_env_key(prefix, name);
+            if let Ok(raw) = std::env::var(&env_key) {
+                if let Ok(value) = from_env_val(raw, option.config_type) {
+                    values.insert(name, value);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ConfigOptionBase, WhspOptions};
+    use std::path::PathBuf;
+
+    fn make_whsp() -> Whsp<'static> {
+        let mut whsp = Whsp {
+            config_set: HashMap::new(),
+            short_options: HashMap::new(),
+            options: WhspOptions {
+                allow_positionals: false,
+                env_prefix: Some("WHSP_SOURCES_TEST"),
+                usage: None,
+            },
+            subcommands: HashMap::new(),
+        };
+        let mut name = ConfigOptionBase::new("string", false, None, None);
+        name.default = Some(ValidValue::String("default-name".into()));
+        whsp.opt(HashMap::from([("name", name)])).unwrap();
+        let mut count = ConfigOptionBase::new("number", false, None, None);
+        count.default = Some(ValidValue::Number(1));
+        whsp.num(HashMap::from([("count", count)])).unwrap();
+        whsp
+    }
+
+    fn temp_file(label: &str, ext: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "whsp_sources_test_{label}_{}.{ext}",
+            std::process::id()
+        ));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn as_str(value: Option<&ValidValue>) -> Option<String> {
+        match value {
+            Some(ValidValue::String(s)) => Some(s.to_string()),
+            _ => None,
+        }
+    }
+
+    fn as_num(value: Option<&ValidValue>) -> Option<i64> {
+        match value {
+            Some(ValidValue::Number(n)) => Some(*n),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn defaults_are_used_when_no_sources_override_them() {
+        let whsp = make_whsp();
+        let result = whsp.load_layered(&[], &[]).unwrap();
+        assert_eq!(as_str(result.values.get("name")), Some("default-name".into()));
+        assert_eq!(as_num(result.values.get("count")), Some(1));
+    }
+
+    #[test]
+    fn toml_overrides_default_and_round_trips_number() {
+        let whsp = make_whsp();
+        let path = temp_file("toml_override", "toml", "name = \"from-toml\"\ncount = 42\n");
+        let result = whsp
+            .load_layered(&[Source::Toml(path.to_str().unwrap())], &[])
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(as_str(result.values.get("name")), Some("from-toml".into()));
+        assert_eq!(as_num(result.values.get("count")), Some(42));
+    }
+
+    #[test]
+    fn json_overrides_toml_for_fields_it_sets() {
+        let whsp = make_whsp();
+        let toml_path = temp_file(
+            "json_over_toml",
+            "toml",
+            "name = \"from-toml\"\ncount = 42\n",
+        );
+        let json_path = temp_file("json_over_toml", "json", "{\"name\": \"from-json\"}");
+        let result = whsp
+            .load_layered(
+                &[
+                    Source::Toml(toml_path.to_str().unwrap()),
+                    Source::Json(json_path.to_str().unwrap()),
+                ],
+                &[],
+            )
+            .unwrap();
+        fs::remove_file(&toml_path).unwrap();
+        fs::remove_file(&json_path).unwrap();
+        assert_eq!(as_str(result.values.get("name")), Some("from-json".into()));
+        assert_eq!(as_num(result.values.get("count")), Some(42));
+    }
+
+    #[test]
+    fn env_overrides_file_sources() {
+        let whsp = make_whsp();
+        let toml_path = temp_file("env_over_file", "toml", "name = \"from-toml\"\n");
+        std::env::set_var("WHSP_SOURCES_TEST_NAME", "from-env");
+        let result = whsp
+            .load_layered(
+                &[Source::Toml(toml_path.to_str().unwrap()), Source::Env],
+                &[],
+            )
+            .unwrap();
+        std::env::remove_var("WHSP_SOURCES_TEST_NAME");
+        fs::remove_file(&toml_path).unwrap();
+        assert_eq!(as_str(result.values.get("name")), Some("from-env".into()));
+    }
+
+    #[test]
+    fn args_take_precedence_over_everything() {
+        let whsp = make_whsp();
+        std::env::set_var("WHSP_SOURCES_TEST_NAME", "from-env");
+        let args = vec!["--name".to_string(), "from-args".to_string()];
+        let result = whsp
+            .load_layered(&[Source::Env, Source::Args], &args)
+            .unwrap();
+        std::env::remove_var("WHSP_SOURCES_TEST_NAME");
+        assert_eq!(as_str(result.values.get("name")), Some("from-args".into()));
+    }
+}